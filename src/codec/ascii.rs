@@ -7,18 +7,24 @@
 use std::convert::Into;
 use types::*;
 
-#[cfg(all(feature = "enable-simd",
-          target_feature = "sse2",
+#[cfg(all(feature = "enable-simd", any(target_arch = "x86", target_arch = "x86_64"),
           not(all(target_os = "macos", debug_assertions))))]
 use simd::u8x16;
-#[cfg(all(feature = "enable-simd",
-          target_feature = "sse2",
+#[cfg(all(feature = "enable-simd", target_arch = "x86_64",
           not(all(target_os = "macos", debug_assertions))))]
-use simd::x86::sse2::Movemask;
-#[cfg(all(feature = "enable-simd",
-          target_feature = "sse2",
+use simd::u8x32;
+#[cfg(all(feature = "enable-simd", any(target_arch = "x86", target_arch = "x86_64"),
+          not(all(target_os = "macos", debug_assertions))))]
+use simd::x86::sse2::Movemask as Sse2Movemask;
+#[cfg(all(feature = "enable-simd", target_arch = "x86_64",
+          not(all(target_os = "macos", debug_assertions))))]
+use simd::x86::avx::Movemask as Avx2Movemask;
+#[cfg(all(feature = "enable-simd", target_arch = "aarch64",
           not(all(target_os = "macos", debug_assertions))))]
-const CHUNK_SIZE: usize = 16;
+use simd::aarch64::neon::u8x16 as neon_u8x16;
+#[cfg(all(feature = "enable-simd", target_arch = "aarch64",
+          not(all(target_os = "macos", debug_assertions))))]
+use simd::aarch64::neon::Movemask as NeonMovemask;
 
 /**
  * ASCII, also known as ISO/IEC 646:US.
@@ -47,6 +53,11 @@ impl RawEncoder for ASCIIEncoder {
     fn from_self(&self) -> Box<RawEncoder> { ASCIIEncoder::new() }
     fn is_ascii_compatible(&self) -> bool { true }
 
+    /// Every input character encodes to exactly one byte.
+    fn max_encoded_len(&self, input_char_count: usize) -> Option<usize> {
+        Some(input_char_count)
+    }
+
     fn raw_feed(&mut self, input: &str, output: &mut ByteWriter) -> (usize, Option<CodecError>) {
         output.writer_hint(input.len());
         match raw_feed(input.as_bytes(), output) {
@@ -80,6 +91,11 @@ impl RawDecoder for ASCIIDecoder {
     fn from_self(&self) -> Box<RawDecoder> { ASCIIDecoder::new() }
     fn is_ascii_compatible(&self) -> bool { true }
 
+    /// Every input byte decodes to exactly one character.
+    fn max_decoded_len(&self, input_byte_count: usize) -> Option<usize> {
+        Some(input_byte_count)
+    }
+
     fn raw_feed(&mut self, input: &[u8], output: &mut StringWriter) -> (usize, Option<CodecError>) {
         output.writer_hint(input.len());
         match raw_feed(input, unsafe { output.as_byte_writer() }) {
@@ -100,19 +116,94 @@ impl RawDecoder for ASCIIDecoder {
     }
 }
 
-#[cfg(any(not(feature = "enable-simd"),
-          not(target_feature = "sse2"),
-          all(target_os = "macos", debug_assertions)))]
 #[inline]
 fn raw_feed(input: &[u8], output: &mut ByteWriter) -> usize {
-    slow_raw_feed(input, output, 0)
+    let sofar = ascii_valid_up_to(input);
+    output.write_bytes(&input[..sofar]);
+    sofar
+}
+
+/// Returns the length of the longest prefix of `input` that consists
+/// entirely of ASCII bytes (i.e. bytes below 0x80).
+///
+/// This is the SIMD-accelerated scan that also powers `ASCIIEncoder` and
+/// `ASCIIDecoder`, exposed for callers that only need to know where a
+/// buffer's ASCII run ends (e.g. to take a zero-copy fast path) and have
+/// no `ByteWriter` to hand.
+///
+/// On x86 and x86_64 the widest vector path available on the running CPU
+/// is picked at runtime (AVX2, then SSE2), so a single binary built for a
+/// generic baseline target still benefits on newer hardware;
+/// `target-feature` build flags are no longer required. aarch64 always
+/// uses the NEON path. Other targets, builds without the `enable-simd`
+/// feature, and macOS debug builds use a scalar scan; this variant is
+/// that scalar scan.
+#[cfg(any(not(feature = "enable-simd"), all(target_os = "macos", debug_assertions)))]
+pub fn ascii_valid_up_to(input: &[u8]) -> usize {
+    slow_ascii_valid_up_to(input, 0)
+}
+
+#[cfg(all(feature = "enable-simd", target_arch = "x86_64",
+          not(all(target_os = "macos", debug_assertions))))]
+pub fn ascii_valid_up_to(input: &[u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        unsafe { avx2_ascii_valid_up_to(input) }
+    } else if is_x86_feature_detected!("sse2") {
+        unsafe { sse2_ascii_valid_up_to(input) }
+    } else {
+        slow_ascii_valid_up_to(input, 0)
+    }
+}
+
+#[cfg(all(feature = "enable-simd", target_arch = "x86",
+          not(all(target_os = "macos", debug_assertions))))]
+pub fn ascii_valid_up_to(input: &[u8]) -> usize {
+    if is_x86_feature_detected!("sse2") {
+        unsafe { sse2_ascii_valid_up_to(input) }
+    } else {
+        slow_ascii_valid_up_to(input, 0)
+    }
 }
 
+#[cfg(all(feature = "enable-simd", target_arch = "aarch64",
+          not(all(target_os = "macos", debug_assertions))))]
+pub fn ascii_valid_up_to(input: &[u8]) -> usize {
+    neon_ascii_valid_up_to(input)
+}
+
+/// Catch-all for targets with no dedicated vector path above (arm32, mips,
+/// powerpc, wasm32, riscv, ...).
 #[cfg(all(feature = "enable-simd",
-          target_feature = "sse2",
+          not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")),
           not(all(target_os = "macos", debug_assertions))))]
-#[inline]
-fn raw_feed(input: &[u8], output: &mut ByteWriter) -> usize {
+pub fn ascii_valid_up_to(input: &[u8]) -> usize {
+    slow_ascii_valid_up_to(input, 0)
+}
+
+#[cfg(all(feature = "enable-simd", target_arch = "x86_64",
+          not(all(target_os = "macos", debug_assertions))))]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_ascii_valid_up_to(input: &[u8]) -> usize {
+    const CHUNK_SIZE: usize = 32;
+    let total = input.len() / CHUNK_SIZE * CHUNK_SIZE;
+    let mut sofar = 0;
+    while sofar != total {
+        let v = u8x32::load(input, sofar);
+        let mask = v.movemask();
+        if mask == 0 {
+            sofar += CHUNK_SIZE;
+        } else {
+            return sofar + (mask as u32).trailing_zeros() as usize;
+        }
+    }
+    slow_ascii_valid_up_to(input, sofar)
+}
+
+#[cfg(all(feature = "enable-simd", any(target_arch = "x86", target_arch = "x86_64"),
+          not(all(target_os = "macos", debug_assertions))))]
+#[target_feature(enable = "sse2")]
+unsafe fn sse2_ascii_valid_up_to(input: &[u8]) -> usize {
+    const CHUNK_SIZE: usize = 16;
     let total = input.len() / CHUNK_SIZE * CHUNK_SIZE;
     let mut sofar = 0;
     while sofar != total {
@@ -121,32 +212,62 @@ fn raw_feed(input: &[u8], output: &mut ByteWriter) -> usize {
         if mask == 0 {
             sofar += CHUNK_SIZE;
         } else {
-            sofar += (mask as u16).trailing_zeros() as usize;
-            output.write_bytes(&input[..sofar]);
-            return sofar;
+            return sofar + (mask as u16).trailing_zeros() as usize;
+        }
+    }
+    slow_ascii_valid_up_to(input, sofar)
+}
+
+#[cfg(all(feature = "enable-simd", target_arch = "aarch64",
+          not(all(target_os = "macos", debug_assertions))))]
+fn neon_ascii_valid_up_to(input: &[u8]) -> usize {
+    const CHUNK_SIZE: usize = 16;
+    let total = input.len() / CHUNK_SIZE * CHUNK_SIZE;
+    let mut sofar = 0;
+    while sofar != total {
+        let v = neon_u8x16::load(input, sofar);
+        let mask = v.movemask();
+        if mask == 0 {
+            sofar += CHUNK_SIZE;
+        } else {
+            return sofar + (mask as u16).trailing_zeros() as usize;
         }
     }
-    slow_raw_feed(input, output, sofar)
+    slow_ascii_valid_up_to(input, sofar)
+}
+
+/// Same as `ascii_valid_up_to`, but scans the UTF-8 bytes of a `str`.
+///
+/// Since ASCII is a subset of UTF-8, the leading all-ASCII run of a `str`
+/// is exactly the leading all-ASCII run of its byte representation.
+pub fn str_ascii_valid_up_to(input: &str) -> usize {
+    ascii_valid_up_to(input.as_bytes())
 }
 
 #[inline]
-fn slow_raw_feed(input: &[u8], output: &mut ByteWriter, sofar: usize)
-               -> usize {
-    let sofar = match input[sofar..].iter().position(|&ch| ch >= 0x80) {
+fn slow_ascii_valid_up_to(input: &[u8], sofar: usize) -> usize {
+    match input[sofar..].iter().position(|&ch| ch >= 0x80) {
         Some(first_error) => sofar + first_error,
         None => input.len(),
-    };
-    output.write_bytes(&input[..sofar]);
-    sofar
+    }
 }
 
 #[cfg(test)]
 mod tests {
     extern crate test;
-    use super::ASCIIEncoding;
+    use super::{ASCIIEncoding, ascii_valid_up_to, str_ascii_valid_up_to};
     use testutils;
     use types::*;
 
+    #[test]
+    fn test_ascii_valid_up_to() {
+        assert_eq!(ascii_valid_up_to(b""), 0);
+        assert_eq!(ascii_valid_up_to(b"hello"), 5);
+        assert_eq!(ascii_valid_up_to(b"hello \xa0world"), 6);
+        assert_eq!(ascii_valid_up_to(b"\xff"), 0);
+        assert_eq!(str_ascii_valid_up_to("hello \u{a0}world"), 6);
+    }
+
     #[test]
     fn test_encoder() {
         let mut e = ASCIIEncoding.raw_encoder();
@@ -158,6 +279,13 @@ mod tests {
         assert_finish_ok!(e, []);
     }
 
+    #[test]
+    fn test_encoder_max_encoded_len() {
+        let e = ASCIIEncoding.raw_encoder();
+        assert_eq!(e.max_encoded_len(0), Some(0));
+        assert_eq!(e.max_encoded_len(42), Some(42));
+    }
+
     #[test]
     fn test_decoder() {
         let mut d = ASCIIEncoding.raw_decoder();
@@ -169,6 +297,13 @@ mod tests {
         assert_finish_ok!(d, "");
     }
 
+    #[test]
+    fn test_decoder_max_decoded_len() {
+        let d = ASCIIEncoding.raw_decoder();
+        assert_eq!(d.max_decoded_len(0), Some(0));
+        assert_eq!(d.max_decoded_len(42), Some(42));
+    }
+
     #[bench]
     fn bench_encode(bencher: &mut test::Bencher) {
         let s = testutils::ASCII_TEXT;
@@ -0,0 +1,152 @@
+// This is a part of rust-encoding.
+// Copyright (c) 2013-2015, Kang Seonghoon.
+// See README.md and LICENSE.txt for details.
+
+//! A generic decoder for single-byte, ASCII-compatible encodings such as
+//! the ISO-8859 family and the windows-125x code pages.
+
+use std::char;
+use std::convert::Into;
+use types::*;
+use codec::ascii;
+
+/// Marks a byte that has no mapping in a `SingleByteDecoder`'s table.
+///
+/// This doubles as the reason a table can only cover the BMP up to
+/// U+FFFE: U+FFFF itself is unrepresentable as a table entry, since it is
+/// reserved as the `UNMAPPED` sentinel.
+pub const UNMAPPED: u16 = 0xffff;
+
+/// A decoder for any single-byte encoding that agrees with ASCII below
+/// 0x80, parameterized by a 128-entry table mapping bytes 0x80-0xFF
+/// (indexed by `byte - 0x80`) to BMP code points.
+///
+/// Rather than branching on every byte, each `raw_feed` call first reuses
+/// the SIMD-accelerated `ascii::ascii_valid_up_to` scan to copy the
+/// longest leading ASCII run in one `write_bytes` call, then maps the
+/// single non-ASCII byte that follows through the table and repeats.
+/// Mostly-ASCII Latin text therefore decodes as long memcpy-style runs
+/// punctuated by rare table lookups.
+///
+/// The table is trusted, not validated: every entry other than `UNMAPPED`
+/// is turned into a `char` via `char::from_u32_unchecked`, so a table must
+/// never map a byte into the surrogate range U+D800-U+DFFF. This is why
+/// construction is `unsafe`: the caller, not this type, is on the hook for
+/// that invariant.
+#[derive(Clone, Copy)]
+pub struct SingleByteDecoder {
+    table: &'static [u16; 128],
+}
+
+impl SingleByteDecoder {
+    /// Creates a decoder backed by `table`.
+    ///
+    /// # Safety
+    ///
+    /// Every entry of `table` other than `UNMAPPED` must be a valid `char`
+    /// value, i.e. not in the surrogate range U+D800-U+DFFF. `raw_feed`
+    /// turns table entries into `char`s via `char::from_u32_unchecked`
+    /// without checking this.
+    pub unsafe fn new(table: &'static [u16; 128]) -> Box<RawDecoder> {
+        Box::new(SingleByteDecoder { table: table })
+    }
+}
+
+impl RawDecoder for SingleByteDecoder {
+    fn from_self(&self) -> Box<RawDecoder> {
+        // Safe: `self.table` already satisfies `new`'s invariant, or this
+        // decoder could not have been constructed in the first place.
+        unsafe { SingleByteDecoder::new(self.table) }
+    }
+    fn is_ascii_compatible(&self) -> bool { true }
+
+    fn raw_feed(&mut self, input: &[u8], output: &mut StringWriter) -> (usize, Option<CodecError>) {
+        // A mapped byte can be a BMP code point up to U+FFFE, which takes
+        // 3 UTF-8 bytes; hint for the worst case of an all-mapped input
+        // rather than underestimating by up to 3x.
+        output.writer_hint(input.len() * 3);
+
+        let mut pos = 0;
+        while pos < input.len() {
+            let ascii_len = ascii::ascii_valid_up_to(&input[pos..]);
+            if ascii_len > 0 {
+                unsafe { output.as_byte_writer() }.write_bytes(&input[pos..pos + ascii_len]);
+                pos += ascii_len;
+            }
+            if pos >= input.len() { break; }
+
+            match self.table[(input[pos] - 0x80) as usize] {
+                UNMAPPED => {
+                    return (pos, Some(CodecError {
+                        upto: pos as isize + 1,
+                        cause: "invalid sequence".into()
+                    }));
+                }
+                mapped => {
+                    // Safe as long as `self.table` upholds its contract:
+                    // no entry other than `UNMAPPED` may fall in
+                    // U+D800-U+DFFF.
+                    output.write_char(unsafe { char::from_u32_unchecked(mapped as u32) });
+                    pos += 1;
+                }
+            }
+        }
+        (pos, None)
+    }
+
+    fn raw_finish(&mut self, _output: &mut StringWriter) -> Option<CodecError> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+    use super::{SingleByteDecoder, UNMAPPED};
+    use testutils;
+    use types::*;
+
+    // A Latin-1-like table for testing: byte `b` maps to code point `b`,
+    // except 0x81 which this fixture leaves unassigned.
+    static TEST_TABLE: [u16; 128] = [
+        0x80, UNMAPPED, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+        0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d, 0x8e, 0x8f,
+        0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97,
+        0x98, 0x99, 0x9a, 0x9b, 0x9c, 0x9d, 0x9e, 0x9f,
+        0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+        0xa8, 0xa9, 0xaa, 0xab, 0xac, 0xad, 0xae, 0xaf,
+        0xb0, 0xb1, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7,
+        0xb8, 0xb9, 0xba, 0xbb, 0xbc, 0xbd, 0xbe, 0xbf,
+        0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        0xc8, 0xc9, 0xca, 0xcb, 0xcc, 0xcd, 0xce, 0xcf,
+        0xd0, 0xd1, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7,
+        0xd8, 0xd9, 0xda, 0xdb, 0xdc, 0xdd, 0xde, 0xdf,
+        0xe0, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7,
+        0xe8, 0xe9, 0xea, 0xeb, 0xec, 0xed, 0xee, 0xef,
+        0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7,
+        0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff,
+    ];
+
+    #[test]
+    fn test_decoder() {
+        let mut d = unsafe { SingleByteDecoder::new(&TEST_TABLE) };
+        assert_feed_ok!(d, [0x41], [], "A");
+        assert_feed_ok!(d, [0x42, 0x43], [], "BC");
+        assert_feed_ok!(d, [], [], "");
+        assert_feed_ok!(d, [0x41, 0x80], [], "A\u{80}");
+        assert_feed_ok!(d, [0xff], [], "\u{ff}");
+        assert_feed_err!(d, [0x41], [0x81], [0x42], "A");
+        assert_finish_ok!(d, "");
+    }
+
+    #[bench]
+    fn bench_decode(bencher: &mut test::Bencher) {
+        let s = testutils::ASCII_TEXT.as_bytes();
+        bencher.bytes = s.len() as u64;
+        bencher.iter(|| test::black_box({
+            let mut d = unsafe { SingleByteDecoder::new(&TEST_TABLE) };
+            let mut out = String::new();
+            d.raw_feed(s, &mut out)
+        }))
+    }
+}
@@ -0,0 +1,162 @@
+// This is a part of rust-encoding.
+// Copyright (c) 2013-2015, Kang Seonghoon.
+// See README.md and LICENSE.txt for details.
+
+//! WHATWG-specific encodings that have no existing standard but are widely
+//! used on the web platform.
+
+use std::convert::Into;
+use types::*;
+use codec::ascii;
+
+/**
+ * x-user-defined, a WHATWG-defined encoding for reading arbitrary binary
+ * data through text APIs.
+ *
+ * Bytes below 0x80 decode to the identical code point, and bytes 0x80-0xFF
+ * decode to the Private Use Area code points U+F780-U+F7FF. This makes the
+ * decoder total: unlike most other encodings, it never rejects any byte
+ * sequence.
+ */
+#[derive(Clone, Copy)]
+pub struct XUserDefinedEncoding;
+
+impl Encoding for XUserDefinedEncoding {
+    fn name(&self) -> &'static str { "x-user-defined" }
+    fn raw_encoder(&self) -> Box<RawEncoder> { XUserDefinedEncoder::new() }
+    fn raw_decoder(&self) -> Box<RawDecoder> { XUserDefinedDecoder::new() }
+}
+
+/// An encoder for x-user-defined.
+#[derive(Clone, Copy)]
+pub struct XUserDefinedEncoder;
+
+impl XUserDefinedEncoder {
+    pub fn new() -> Box<RawEncoder> { Box::new(XUserDefinedEncoder) }
+}
+
+impl RawEncoder for XUserDefinedEncoder {
+    fn from_self(&self) -> Box<RawEncoder> { XUserDefinedEncoder::new() }
+    fn is_ascii_compatible(&self) -> bool { true }
+
+    fn raw_feed(&mut self, input: &str, output: &mut ByteWriter) -> (usize, Option<CodecError>) {
+        output.writer_hint(input.len());
+
+        let mut pos = 0;
+        while pos < input.len() {
+            let ascii_len = ascii::ascii_valid_up_to(&input.as_bytes()[pos..]);
+            if ascii_len > 0 {
+                output.write_bytes(&input.as_bytes()[pos..pos + ascii_len]);
+                pos += ascii_len;
+            }
+            if pos >= input.len() { break; }
+
+            let c = input[pos..].chars().next().unwrap();
+            let cp = c as u32;
+            if cp >= 0xf780 && cp <= 0xf7ff {
+                output.write_byte((cp - 0xf780 + 0x80) as u8);
+                pos += c.len_utf8();
+            } else {
+                return (pos, Some(CodecError {
+                    upto: (pos + c.len_utf8()) as isize,
+                    cause: "unrepresentable character".into()
+                }));
+            }
+        }
+        (pos, None)
+    }
+
+    fn raw_finish(&mut self, _output: &mut ByteWriter) -> Option<CodecError> {
+        None
+    }
+}
+
+/// A decoder for x-user-defined.
+#[derive(Clone, Copy)]
+pub struct XUserDefinedDecoder;
+
+impl XUserDefinedDecoder {
+    pub fn new() -> Box<RawDecoder> { Box::new(XUserDefinedDecoder) }
+}
+
+impl RawDecoder for XUserDefinedDecoder {
+    fn from_self(&self) -> Box<RawDecoder> { XUserDefinedDecoder::new() }
+    fn is_ascii_compatible(&self) -> bool { true }
+
+    fn raw_feed(&mut self, input: &[u8], output: &mut StringWriter) -> (usize, Option<CodecError>) {
+        // Unlike ASCII, a non-ASCII byte here decodes to a PUA code point
+        // (U+F780-U+F7FF), which takes 3 UTF-8 bytes; hint for the worst
+        // case of an all-non-ASCII input rather than underestimating by
+        // up to 3x.
+        output.writer_hint(input.len() * 3);
+
+        let mut pos = 0;
+        while pos < input.len() {
+            let ascii_len = ascii::ascii_valid_up_to(&input[pos..]);
+            if ascii_len > 0 {
+                unsafe { output.as_byte_writer() }.write_bytes(&input[pos..pos + ascii_len]);
+                pos += ascii_len;
+            }
+            if pos >= input.len() { break; }
+
+            let b = input[pos];
+            output.write_char(unsafe { ::std::char::from_u32_unchecked(0xf700 + b as u32) });
+            pos += 1;
+        }
+        (pos, None)
+    }
+
+    fn raw_finish(&mut self, _output: &mut StringWriter) -> Option<CodecError> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+    use super::XUserDefinedEncoding;
+    use testutils;
+    use types::*;
+
+    #[test]
+    fn test_encoder() {
+        let mut e = XUserDefinedEncoding.raw_encoder();
+        assert_feed_ok!(e, "A", "", [0x41]);
+        assert_feed_ok!(e, "BC", "", [0x42, 0x43]);
+        assert_feed_ok!(e, "", "", []);
+        assert_feed_ok!(e, "\u{f780}", "", [0x80]);
+        assert_feed_ok!(e, "\u{f7ff}", "", [0xff]);
+        assert_feed_err!(e, "", "\u{a0}", "", []);
+        assert_feed_err!(e, "X", "\u{a0}", "Z", [0x58]);
+        assert_finish_ok!(e, []);
+    }
+
+    #[test]
+    fn test_decoder() {
+        let mut d = XUserDefinedEncoding.raw_decoder();
+        assert_feed_ok!(d, [0x41], [], "A");
+        assert_feed_ok!(d, [0x42, 0x43], [], "BC");
+        assert_feed_ok!(d, [], [], "");
+        assert_feed_ok!(d, [0x80], [], "\u{f780}");
+        assert_feed_ok!(d, [0xff], [], "\u{f7ff}");
+        assert_finish_ok!(d, "");
+    }
+
+    #[bench]
+    fn bench_encode(bencher: &mut test::Bencher) {
+        let s = testutils::ASCII_TEXT;
+        bencher.bytes = s.len() as u64;
+        bencher.iter(|| test::black_box({
+            XUserDefinedEncoding.encode(s, EncoderTrap::Strict)
+        }))
+    }
+
+    #[bench]
+    fn bench_decode(bencher: &mut test::Bencher) {
+        let s = testutils::ASCII_TEXT.as_bytes();
+        bencher.bytes = s.len() as u64;
+        bencher.iter(|| test::black_box({
+            XUserDefinedEncoding.decode(s, DecoderTrap::Strict)
+        }))
+    }
+}
@@ -0,0 +1,221 @@
+// This is a part of rust-encoding.
+// Copyright (c) 2013-2015, Kang Seonghoon.
+// See README.md and LICENSE.txt for details.
+
+//! Base types used throughout the library.
+
+use std::borrow::Cow;
+
+/// A byte sink used by `RawEncoder`.
+pub trait ByteWriter {
+    /// Hints the expected length of the output, so the sink can reserve
+    /// capacity up front. Purely advisory.
+    fn writer_hint(&mut self, expectedlen: usize);
+
+    /// Writes a single byte.
+    fn write_byte(&mut self, byte: u8);
+
+    /// Writes a sequence of bytes.
+    fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+impl ByteWriter for Vec<u8> {
+    fn writer_hint(&mut self, expectedlen: usize) { self.reserve(expectedlen); }
+    fn write_byte(&mut self, byte: u8) { self.push(byte); }
+    fn write_bytes(&mut self, bytes: &[u8]) { self.extend_from_slice(bytes); }
+}
+
+/// A character sink used by `RawDecoder`.
+pub trait StringWriter {
+    /// Hints the expected length of the output, so the sink can reserve
+    /// capacity up front. Purely advisory.
+    fn writer_hint(&mut self, expectedlen: usize);
+
+    /// Writes a single character.
+    fn write_char(&mut self, c: char);
+
+    /// Writes a string slice.
+    fn write_str(&mut self, s: &str);
+
+    /// Returns the underlying `ByteWriter`.
+    ///
+    /// This is only safe to use for writing byte sequences that are valid
+    /// UTF-8 on their own, e.g. an ASCII-compatible run of bytes below
+    /// 0x80 -- the caller is responsible for upholding that invariant, as
+    /// nothing here checks it.
+    unsafe fn as_byte_writer(&mut self) -> &mut ByteWriter;
+}
+
+impl StringWriter for String {
+    fn writer_hint(&mut self, expectedlen: usize) { self.reserve(expectedlen); }
+    fn write_char(&mut self, c: char) { self.push(c); }
+    fn write_str(&mut self, s: &str) { self.push_str(s); }
+    unsafe fn as_byte_writer(&mut self) -> &mut ByteWriter { self.as_mut_vec() }
+}
+
+/// Describes a problem encountered while encoding or decoding.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CodecError {
+    /// The end of the problematic sequence, relative to the start of the
+    /// input passed to the `raw_feed` call that produced this error.
+    pub upto: isize,
+    /// A human-readable description of the problem.
+    pub cause: Cow<'static, str>,
+}
+
+/// A trap handler invoked by `Encoding::encode` when a character cannot be
+/// represented in the target encoding.
+pub enum EncoderTrap {
+    /// Fail immediately, returning the error.
+    Strict,
+    /// Skip the unrepresentable character.
+    Ignore,
+    /// Replace the unrepresentable character with a fixed placeholder.
+    Replace,
+}
+
+/// A trap handler invoked by `Encoding::decode` when a byte sequence is
+/// invalid in the source encoding.
+pub enum DecoderTrap {
+    /// Fail immediately, returning the error.
+    Strict,
+    /// Skip the invalid sequence.
+    Ignore,
+    /// Replace the invalid sequence with U+FFFD.
+    Replace,
+}
+
+/// An encoder that converts a Unicode string into a byte sequence.
+pub trait RawEncoder {
+    /// Creates a fresh encoder of the same kind as this one.
+    fn from_self(&self) -> Box<RawEncoder>;
+
+    /// Returns true if this encoding is compatible with ASCII, i.e. every
+    /// ASCII scalar value encodes to its identical byte value.
+    fn is_ascii_compatible(&self) -> bool { false }
+
+    /// The maximum number of bytes that encoding `input_char_count`
+    /// characters could possibly produce, or `None` if that count cannot
+    /// be computed (e.g. it would overflow). Encodings with a fixed
+    /// bytes-per-character ratio should override this so callers can
+    /// preallocate an exact output buffer instead of relying on
+    /// `writer_hint` alone.
+    fn max_encoded_len(&self, input_char_count: usize) -> Option<usize> {
+        let _ = input_char_count;
+        None
+    }
+
+    /// Feeds a string to the encoder, returning the number of bytes
+    /// consumed and, if the input could not be fully represented, the
+    /// resulting error.
+    fn raw_feed(&mut self, input: &str, output: &mut ByteWriter) -> (usize, Option<CodecError>);
+
+    /// Finishes the stream, flushing any buffered state.
+    fn raw_finish(&mut self, output: &mut ByteWriter) -> Option<CodecError>;
+}
+
+/// A decoder that converts a byte sequence into a Unicode string.
+pub trait RawDecoder {
+    /// Creates a fresh decoder of the same kind as this one.
+    fn from_self(&self) -> Box<RawDecoder>;
+
+    /// Returns true if this encoding is compatible with ASCII, i.e. every
+    /// byte below 0x80 decodes to its identical scalar value.
+    fn is_ascii_compatible(&self) -> bool { false }
+
+    /// The maximum number of characters that decoding
+    /// `input_byte_count` bytes could possibly produce, or `None` if that
+    /// count cannot be computed (e.g. it would overflow). Encodings with a
+    /// fixed bytes-per-character ratio should override this so callers can
+    /// preallocate an exact output buffer instead of relying on
+    /// `writer_hint` alone.
+    fn max_decoded_len(&self, input_byte_count: usize) -> Option<usize> {
+        let _ = input_byte_count;
+        None
+    }
+
+    /// Feeds a byte sequence to the decoder, returning the number of bytes
+    /// consumed and, if the input was not valid, the resulting error.
+    fn raw_feed(&mut self, input: &[u8], output: &mut StringWriter) -> (usize, Option<CodecError>);
+
+    /// Finishes the stream, flushing any buffered state.
+    fn raw_finish(&mut self, output: &mut StringWriter) -> Option<CodecError>;
+}
+
+/// A character encoding.
+pub trait Encoding {
+    /// An encoding's canonical, lowercase name (e.g. `"ascii"`).
+    fn name(&self) -> &'static str;
+
+    /// Creates a new `RawEncoder`.
+    fn raw_encoder(&self) -> Box<RawEncoder>;
+
+    /// Creates a new `RawDecoder`.
+    fn raw_decoder(&self) -> Box<RawDecoder>;
+
+    /// Encodes a string into a byte sequence, handling errors with `trap`.
+    fn encode(&self, input: &str, trap: EncoderTrap) -> Result<Vec<u8>, Cow<'static, str>> {
+        let mut encoder = self.raw_encoder();
+        let mut output = Vec::new();
+        if let Some(cap) = encoder.max_encoded_len(input.chars().count()) {
+            output.writer_hint(cap);
+        }
+        let mut pos = 0;
+        loop {
+            let (processed, err) = encoder.raw_feed(&input[pos..], &mut output);
+            pos += processed;
+            match err {
+                Some(err) => match trap {
+                    EncoderTrap::Strict => return Err(err.cause),
+                    EncoderTrap::Ignore => {
+                        pos = (pos as isize + (err.upto - processed as isize)).max(pos as isize) as usize;
+                    }
+                    EncoderTrap::Replace => {
+                        output.write_byte(b'?');
+                        pos = (pos as isize + (err.upto - processed as isize)).max(pos as isize) as usize;
+                    }
+                },
+                None => break,
+            }
+        }
+        if let Some(err) = encoder.raw_finish(&mut output) {
+            if let EncoderTrap::Strict = trap {
+                return Err(err.cause);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Decodes a byte sequence into a string, handling errors with `trap`.
+    fn decode(&self, input: &[u8], trap: DecoderTrap) -> Result<String, Cow<'static, str>> {
+        let mut decoder = self.raw_decoder();
+        let mut output = String::new();
+        if let Some(cap) = decoder.max_decoded_len(input.len()) {
+            output.writer_hint(cap);
+        }
+        let mut pos = 0;
+        loop {
+            let (processed, err) = decoder.raw_feed(&input[pos..], &mut output);
+            pos += processed;
+            match err {
+                Some(err) => match trap {
+                    DecoderTrap::Strict => return Err(err.cause),
+                    DecoderTrap::Ignore => {
+                        pos = (pos as isize + (err.upto - processed as isize)).max(pos as isize) as usize;
+                    }
+                    DecoderTrap::Replace => {
+                        output.write_char('\u{fffd}');
+                        pos = (pos as isize + (err.upto - processed as isize)).max(pos as isize) as usize;
+                    }
+                },
+                None => break,
+            }
+        }
+        if let Some(err) = decoder.raw_finish(&mut output) {
+            if let DecoderTrap::Strict = trap {
+                return Err(err.cause);
+            }
+        }
+        Ok(output)
+    }
+}